@@ -1,11 +1,13 @@
 //! Possible ZIP compression methods.
 
 /// Compression methods for the contents of a ZIP file.
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CompressionMethod
 {
     /// The file is stored (no compression)
     Stored,
+    /// The file is Deflated
+    Deflated,
     /// Unsupported compression method
     Unsupported(u16),
 }
@@ -15,6 +17,7 @@ impl CompressionMethod {
     pub fn from_u16(val: u16) -> CompressionMethod {
         match val {
             0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflated,
             v => CompressionMethod::Unsupported(v),
         }
     }
@@ -23,6 +26,7 @@ impl CompressionMethod {
     pub fn to_u16(self) -> u16 {
         match self {
             CompressionMethod::Stored => 0,
+            CompressionMethod::Deflated => 8,
             CompressionMethod::Unsupported(v) => v,
         }
     }
@@ -52,5 +56,6 @@ mod test {
         }
 
         check_match(CompressionMethod::Stored);
+        check_match(CompressionMethod::Deflated);
     }
 }
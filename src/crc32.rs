@@ -0,0 +1,115 @@
+//! Helper module to compute a CRC32 checksum
+
+use std::io;
+use std::io::prelude::*;
+
+/// Update a running CRC32 checksum with a single byte, using the standard
+/// IEEE 802.3 polynomial.
+pub fn crc32_update(crc: u32, byte: u8) -> u32
+{
+    let mut c = crc ^ byte as u32;
+    for _ in 0..8
+    {
+        c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+    }
+    c
+}
+
+/// Reader that validates the CRC32 when it reaches the EOF
+pub struct Crc32Reader<R>
+{
+    inner: R,
+    crc32: u32,
+    check: u32,
+}
+
+impl<R: Read> Crc32Reader<R>
+{
+    /// Get a new Crc32Reader which validates the checksum when reaching the EOF.
+    pub fn new(inner: R, checksum: u32) -> Crc32Reader<R>
+    {
+        Crc32Reader { inner: inner, crc32: 0xFFFFFFFF, check: checksum }
+    }
+
+    fn check_matches(&self) -> bool
+    {
+        self.crc32 ^ 0xFFFFFFFF == self.check
+    }
+}
+
+impl<R: Read> Read for Crc32Reader<R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+        let count = try!(self.inner.read(buf));
+        if count == 0
+        {
+            if self.check_matches()
+            {
+                return Ok(0);
+            }
+            else
+            {
+                return Err(io::Error::new(io::ErrorKind::Other, "Invalid checksum"));
+            }
+        }
+        for &byte in &buf[0..count]
+        {
+            self.crc32 = crc32_update(self.crc32, byte);
+        }
+        Ok(count)
+    }
+}
+
+/// Reader that accumulates a running CRC32 of a stream whose expected
+/// checksum isn't known until the stream has been fully read, such as a
+/// streamed entry whose checksum trails the data in a data descriptor.
+/// Unlike `Crc32Reader`, this does not validate on its own; the caller reads
+/// back `crc32()` once EOF is reached and compares it against the checksum
+/// recovered from elsewhere.
+pub struct Crc32Accumulator<R>
+{
+    inner: R,
+    crc32: u32,
+}
+
+impl<R: Read> Crc32Accumulator<R>
+{
+    /// Get a new Crc32Accumulator which computes the checksum of everything read through it
+    pub fn new(inner: R) -> Crc32Accumulator<R>
+    {
+        Crc32Accumulator { inner: inner, crc32: 0xFFFFFFFF }
+    }
+
+    /// The CRC32 of all bytes read through this reader so far
+    pub fn crc32(&self) -> u32
+    {
+        self.crc32 ^ 0xFFFFFFFF
+    }
+
+    /// Borrow the wrapped reader
+    pub fn get_ref(&self) -> &R
+    {
+        &self.inner
+    }
+
+    /// Mutably borrow the wrapped reader, to drive it directly rather than
+    /// through this reader's `Read` impl
+    pub fn get_mut(&mut self) -> &mut R
+    {
+        &mut self.inner
+    }
+}
+
+impl<R: Read> Read for Crc32Accumulator<R>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+    {
+        let count = try!(self.inner.read(buf));
+        for &byte in &buf[0..count]
+        {
+            self.crc32 = crc32_update(self.crc32, byte);
+        }
+        Ok(count)
+    }
+}
@@ -1,4 +1,4 @@
-//! A basic ZipReader/Writer crate
+//! A basic ZipReader crate
 
 #![warn(missing_docs)]
 
@@ -7,7 +7,7 @@ extern crate flate2;
 extern crate podio;
 
 pub use read::ZipArchive;
-pub use write::ZipWriter;
+pub use read::read_zipfile_from_stream;
 pub use compression::CompressionMethod;
 
 mod util;
@@ -16,6 +16,6 @@ mod crc32;
 mod types;
 pub mod read;
 mod compression;
-pub mod write;
 mod cp437;
 pub mod result;
+mod zipcrypto;
@@ -1,6 +1,6 @@
 //! Structs for reading a ZIP archive
 
-use crc32::Crc32Reader;
+use crc32::{Crc32Reader, Crc32Accumulator};
 use compression::CompressionMethod;
 use spec;
 use result::{ZipResult, ZipError};
@@ -11,6 +11,10 @@ use util;
 use podio::{ReadPodExt, LittleEndian};
 use types::ZipFileData;
 use cp437::FromCp437;
+use flate2::read::DeflateDecoder;
+use zipcrypto::{ZipCryptoReader, ZipCryptoKeys};
+use std::borrow::Cow;
+use std::collections::VecDeque;
 
 /// Wrapper for reading the contents of a ZIP file.
 ///
@@ -43,16 +47,25 @@ pub struct ZipArchive<R: Read + io::Seek>
     reader: R,
     files: Vec<ZipFileData>,
     names_map: HashMap<String, usize>,
+    comment: Vec<u8>,
 }
 
 enum ZipFileReader<'a> {
     Stored(Crc32Reader<io::Take<&'a mut Read>>),
+    Deflated(Crc32Reader<DeflateDecoder<io::Take<&'a mut Read>>>),
+    StoredDecrypted(Crc32Reader<ZipCryptoReader<io::Take<&'a mut Read>>>),
+    DeflatedDecrypted(Crc32Reader<DeflateDecoder<ZipCryptoReader<io::Take<&'a mut Read>>>>),
+    StoredStreaming(Crc32Accumulator<DataDescriptorReader<'a>>),
+    DeflatedStreaming(Crc32Accumulator<DeflateDecoder<DataDescriptorReader<'a>>>),
 }
 
 /// A struct for reading a zip file
 pub struct ZipFile<'a> {
-    data: &'a ZipFileData,
+    data: Cow<'a, ZipFileData>,
     reader: ZipFileReader<'a>,
+    /// Set once a streamed entry's trailing data descriptor has been read
+    /// and its CRC/sizes reconciled back onto `data`
+    streaming_finished: bool,
 }
 
 fn unsupported_zip_error<T>(detail: &'static str) -> ZipResult<T>
@@ -60,6 +73,11 @@ fn unsupported_zip_error<T>(detail: &'static str) -> ZipResult<T>
     Err(ZipError::UnsupportedArchive(detail))
 }
 
+/// "version made by" host system identifying a Unix-produced archive
+const UNIX_HOST_ID: u16 = 3;
+/// The `S_IFDIR` bit of a Unix file mode, marking a directory entry
+const S_IFDIR: u32 = 0o040000;
+
 impl<R: Read+io::Seek> ZipArchive<R>
 {
     /// Opens a Zip archive and parses the central directory
@@ -68,8 +86,9 @@ impl<R: Read+io::Seek> ZipArchive<R>
 
         if footer.disk_number != footer.disk_with_central_directory { return unsupported_zip_error("Support for multi-disk files is not implemented") }
 
-        let directory_start = footer.central_directory_offset as u64;
+        let directory_start = footer.central_directory_offset;
         let number_of_files = footer.number_of_files_on_this_disk as usize;
+        let comment = footer.zip_file_comment;
 
         let mut files = Vec::with_capacity(number_of_files);
         let mut names_map = HashMap::new();
@@ -82,7 +101,7 @@ impl<R: Read+io::Seek> ZipArchive<R>
             files.push(file);
         }
 
-        Ok(ZipArchive { reader: reader, files: files, names_map: names_map })
+        Ok(ZipArchive { reader: reader, files: files, names_map: names_map, comment: comment })
     }
 
     /// Number of files contained in this zip.
@@ -102,42 +121,98 @@ impl<R: Read+io::Seek> ZipArchive<R>
         self.files.len()
     }
 
+    /// Get the comment of the zip archive
+    pub fn comment(&self) -> &[u8]
+    {
+        &self.comment
+    }
+
     /// Search for a file entry by name
     pub fn by_name<'a>(&'a mut self, name: &str) -> ZipResult<ZipFile<'a>>
+    {
+        self.by_name_decrypt(name, &[])
+    }
+
+    /// Search for a file entry by name, decrypting it with the given password if necessary
+    pub fn by_name_decrypt<'a>(&'a mut self, name: &str, password: &[u8]) -> ZipResult<ZipFile<'a>>
     {
         let index = match self.names_map.get(name) {
             Some(index) => *index,
             None => { return Err(ZipError::FileNotFound); },
         };
-        self.by_index(index)
+        self.by_index_decrypt(index, password)
     }
 
     /// Get a contained file by index
     pub fn by_index<'a>(&'a mut self, file_number: usize) -> ZipResult<ZipFile<'a>>
+    {
+        self.by_index_decrypt(file_number, &[])
+    }
+
+    /// Get a contained file by index, decrypting it with the given password if necessary
+    pub fn by_index_decrypt<'a>(&'a mut self, file_number: usize, password: &[u8]) -> ZipResult<ZipFile<'a>>
     {
         if file_number >= self.files.len() { return Err(ZipError::FileNotFound); }
         let ref data = self.files[file_number];
         let pos = data.data_start;
 
-        if data.encrypted
-        {
-            return unsupported_zip_error("Encrypted files are not supported")
-        }
-
         try!(self.reader.seek(io::SeekFrom::Start(pos)));
-        let limit_reader = (self.reader.by_ref() as &mut Read).take(data.compressed_size);
+        let mut limit_reader = (self.reader.by_ref() as &mut Read).take(data.compressed_size);
 
-        let reader = match data.compression_method
+        let reader = if data.encrypted
         {
-            CompressionMethod::Stored =>
+            if password.is_empty()
             {
-                ZipFileReader::Stored(Crc32Reader::new(
-                    limit_reader,
-                    data.crc32))
-            },
-            _ => return unsupported_zip_error("Compression method not supported"),
+                return unsupported_zip_error("Encrypted files are not supported without a password")
+            }
+
+            let mut keys = ZipCryptoKeys::derive(password);
+            let mut header = [0u8; ::zipcrypto::ZIPCRYPTO_HEADER_SIZE];
+            try!(limit_reader.read_exact(&mut header));
+            for byte in &mut header {
+                *byte = keys.decrypt_byte(*byte);
+            }
+            if header[11] != (data.crc32 >> 24) as u8
+            {
+                return Err(ZipError::InvalidPassword);
+            }
+
+            let crypto_reader = ZipCryptoReader::new(limit_reader, keys);
+            match data.compression_method
+            {
+                CompressionMethod::Stored =>
+                {
+                    ZipFileReader::StoredDecrypted(Crc32Reader::new(crypto_reader, data.crc32))
+                },
+                CompressionMethod::Deflated =>
+                {
+                    let deflate_reader = DeflateDecoder::new(crypto_reader);
+                    ZipFileReader::DeflatedDecrypted(Crc32Reader::new(deflate_reader, data.crc32))
+                },
+                _ => return unsupported_zip_error("Compression method not supported"),
+            }
+        }
+        else
+        {
+            match data.compression_method
+            {
+                CompressionMethod::Stored =>
+                {
+                    ZipFileReader::Stored(Crc32Reader::new(
+                        limit_reader,
+                        data.crc32))
+                },
+                CompressionMethod::Deflated =>
+                {
+                    let deflate_reader = DeflateDecoder::new(limit_reader);
+                    ZipFileReader::Deflated(Crc32Reader::new(
+                        deflate_reader,
+                        data.crc32))
+                },
+                _ => return unsupported_zip_error("Compression method not supported"),
+            }
         };
-        Ok(ZipFile { reader: reader, data: data })
+        Ok(ZipFile { reader: reader, data: Cow::Borrowed(data), streaming_finished: false })
     }
 
     /// Unwrap and return the inner reader object
@@ -147,6 +222,47 @@ impl<R: Read+io::Seek> ZipArchive<R>
     {
         self.reader
     }
+
+    /// Extract a Zip archive into a directory, overwriting files if they
+    /// already exist. Paths are sanitized with `ZipFile::sanitized_name`.
+    pub fn extract<P: AsRef<::std::path::Path>>(&mut self, directory: P) -> ZipResult<()>
+    {
+        use std::fs;
+
+        for i in 0..self.len()
+        {
+            let mut file = try!(self.by_index(i));
+            let outpath = directory.as_ref().join(file.sanitized_name());
+
+            if file.is_dir()
+            {
+                try!(fs::create_dir_all(&outpath));
+            }
+            else
+            {
+                if let Some(p) = outpath.parent()
+                {
+                    if !p.exists()
+                    {
+                        try!(fs::create_dir_all(&p));
+                    }
+                }
+                let mut outfile = try!(fs::File::create(&outpath));
+                try!(io::copy(&mut file, &mut outfile));
+            }
+
+            // Get and set permissions
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                if let Some(mode) = file.unix_mode()
+                {
+                    try!(fs::set_permissions(&outpath, fs::Permissions::from_mode(mode)));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 fn central_header_to_zip_file<R: Read+io::Seek>(reader: &mut R) -> ZipResult<ZipFileData>
@@ -158,7 +274,7 @@ fn central_header_to_zip_file<R: Read+io::Seek>(reader: &mut R) -> ZipResult<Zip
         return Err(ZipError::InvalidArchive("Invalid Central Directory header"))
     }
 
-    try!(reader.read_u16::<LittleEndian>());
+    let version_made_by = try!(reader.read_u16::<LittleEndian>());
     try!(reader.read_u16::<LittleEndian>());
     let flags = try!(reader.read_u16::<LittleEndian>());
     let encrypted = flags & 1 == 1;
@@ -174,7 +290,7 @@ fn central_header_to_zip_file<R: Read+io::Seek>(reader: &mut R) -> ZipResult<Zip
     let file_comment_length = try!(reader.read_u16::<LittleEndian>()) as usize;
     try!(reader.read_u16::<LittleEndian>());
     try!(reader.read_u16::<LittleEndian>());
-    try!(reader.read_u32::<LittleEndian>());
+    let external_file_attributes = try!(reader.read_u32::<LittleEndian>());
     let offset = try!(reader.read_u32::<LittleEndian>()) as u64;
     let file_name_raw = try!(ReadPodExt::read_exact(reader, file_name_length));
     let extra_field = try!(ReadPodExt::read_exact(reader, extra_field_length));
@@ -194,21 +310,10 @@ fn central_header_to_zip_file<R: Read+io::Seek>(reader: &mut R) -> ZipResult<Zip
     // Remember end of central header
     let return_position = try!(reader.seek(io::SeekFrom::Current(0)));
 
-    // Parse local header
-    try!(reader.seek(io::SeekFrom::Start(offset)));
-    let signature = try!(reader.read_u32::<LittleEndian>());
-    if signature != spec::LOCAL_FILE_HEADER_SIGNATURE
-    {
-        return Err(ZipError::InvalidArchive("Invalid local file header"))
-    }
-
-    try!(reader.seek(io::SeekFrom::Current(22)));
-    let file_name_length = try!(reader.read_u16::<LittleEndian>()) as u64;
-    let extra_field_length = try!(reader.read_u16::<LittleEndian>()) as u64;
-    let magic_and_header = 4 + 22 + 2 + 2;
-    let data_start = offset + magic_and_header + file_name_length + extra_field_length;
-
-    // Construct the result
+    // Construct the result, using the raw 32-bit fields. Any of them set to
+    // their sentinel value (0xFFFFFFFF, or 0xFFFF for the entry count that's
+    // checked by the caller) means the real value lives in a ZIP64 extra
+    // field, which parse_extra_field() will fill in below.
     let mut result = ZipFileData
     {
         encrypted: encrypted,
@@ -220,10 +325,37 @@ fn central_header_to_zip_file<R: Read+io::Seek>(reader: &mut R) -> ZipResult<Zip
         file_name: file_name,
         file_comment: file_comment,
         header_start: offset,
-        data_start: data_start,
+        data_start: 0,
+        unix_mode: match version_made_by >> 8
+        {
+            UNIX_HOST_ID => Some(external_file_attributes >> 16),
+            _ => None,
+        },
+        unix_mtime: None,
+        unix_atime: None,
+        unix_ctime: None,
     };
 
-    try!(parse_extra_field(&mut result, &*extra_field));
+    let uncompressed_size_is_zip64 = uncompressed_size == ::std::u32::MAX;
+    let compressed_size_is_zip64 = compressed_size == ::std::u32::MAX;
+    let header_start_is_zip64 = offset == ::std::u32::MAX as u64;
+    try!(parse_extra_field(&mut result, &*extra_field, uncompressed_size_is_zip64, compressed_size_is_zip64, header_start_is_zip64));
+
+    let header_start = result.header_start;
+
+    // Parse local header
+    try!(reader.seek(io::SeekFrom::Start(header_start)));
+    let signature = try!(reader.read_u32::<LittleEndian>());
+    if signature != spec::LOCAL_FILE_HEADER_SIGNATURE
+    {
+        return Err(ZipError::InvalidArchive("Invalid local file header"))
+    }
+
+    try!(reader.seek(io::SeekFrom::Current(22)));
+    let file_name_length = try!(reader.read_u16::<LittleEndian>()) as u64;
+    let extra_field_length = try!(reader.read_u16::<LittleEndian>()) as u64;
+    let magic_and_header = 4 + 22 + 2 + 2;
+    result.data_start = header_start + magic_and_header + file_name_length + extra_field_length;
 
     // Go back after the central header
     try!(reader.seek(io::SeekFrom::Start(return_position)));
@@ -231,7 +363,7 @@ fn central_header_to_zip_file<R: Read+io::Seek>(reader: &mut R) -> ZipResult<Zip
     Ok(result)
 }
 
-fn parse_extra_field(_file: &mut ZipFileData, data: &[u8]) -> ZipResult<()>
+fn parse_extra_field(file: &mut ZipFileData, data: &[u8], uncompressed_size_is_zip64: bool, compressed_size_is_zip64: bool, header_start_is_zip64: bool) -> ZipResult<()>
 {
     let mut reader = io::Cursor::new(data);
 
@@ -239,20 +371,195 @@ fn parse_extra_field(_file: &mut ZipFileData, data: &[u8]) -> ZipResult<()>
     {
         let kind = try!(reader.read_u16::<LittleEndian>());
         let len = try!(reader.read_u16::<LittleEndian>());
+        let block_end = reader.position() + len as u64;
         match kind
         {
-            _ => try!(reader.seek(io::SeekFrom::Current(len as i64))),
+            0x0001 =>
+            {
+                // Zip64 extended information extra field
+                if uncompressed_size_is_zip64
+                {
+                    file.uncompressed_size = try!(reader.read_u64::<LittleEndian>());
+                }
+                if compressed_size_is_zip64
+                {
+                    file.compressed_size = try!(reader.read_u64::<LittleEndian>());
+                }
+                if header_start_is_zip64
+                {
+                    file.header_start = try!(reader.read_u64::<LittleEndian>());
+                }
+                // disk start number, unused since multi-disk archives aren't supported
+            },
+            0x5455 =>
+            {
+                // Extended timestamp extra field. The central directory copy
+                // of this field often only carries the mtime even when the
+                // flags claim atime/ctime are present too, so stop as soon as
+                // we run out of bytes in this block rather than trusting the
+                // flags blindly.
+                let flags = try!(reader.read_u8());
+                if flags & 1 != 0 && reader.position() + 4 <= block_end
+                {
+                    file.unix_mtime = Some(try!(reader.read_i32::<LittleEndian>()) as i64);
+                }
+                if flags & 2 != 0 && reader.position() + 4 <= block_end
+                {
+                    file.unix_atime = Some(try!(reader.read_i32::<LittleEndian>()) as i64);
+                }
+                if flags & 4 != 0 && reader.position() + 4 <= block_end
+                {
+                    file.unix_ctime = Some(try!(reader.read_i32::<LittleEndian>()) as i64);
+                }
+            },
+            _ => {},
         };
+        try!(reader.seek(io::SeekFrom::Start(block_end)));
     }
     Ok(())
 }
 
+/// Signature marking a trailing data descriptor, used when sizes are not
+/// known up-front (general purpose bit flag 3)
+const DATA_DESCRIPTOR_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x07, 0x08];
+
+fn read_u32_le(reader: &mut Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    try!(reader.read_exact(&mut buf));
+    Ok(buf[0] as u32 | (buf[1] as u32) << 8 | (buf[2] as u32) << 16 | (buf[3] as u32) << 24)
+}
+
+/// A reader that passes bytes through unchanged until it encounters the
+/// data descriptor signature, at which point it stops yielding bytes and
+/// consumes the trailing CRC-32/sizes fields from the underlying reader.
+struct DataDescriptorReader<'a> {
+    inner: &'a mut Read,
+    window: VecDeque<u8>,
+    done: bool,
+    /// CRC-32 recovered from the trailing data descriptor, once `done`
+    crc32: u32,
+    /// Compressed size recovered from the trailing data descriptor, once `done`
+    compressed_size: u64,
+    /// Uncompressed size recovered from the trailing data descriptor, once `done`
+    uncompressed_size: u64,
+}
+
+impl<'a> DataDescriptorReader<'a> {
+    fn new(inner: &'a mut Read) -> DataDescriptorReader<'a> {
+        DataDescriptorReader {
+            inner: inner,
+            window: VecDeque::with_capacity(4),
+            done: false,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+        }
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        self.crc32 = try!(read_u32_le(self.inner));
+        self.compressed_size = try!(read_u32_le(self.inner)) as u64;
+        self.uncompressed_size = try!(read_u32_le(self.inner)) as u64;
+        Ok(())
+    }
+}
+
+impl<'a> Read for DataDescriptorReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() { return Ok(0); }
+
+        let mut written = 0;
+        while written < buf.len() {
+            while self.window.len() < 4 {
+                let mut byte = [0u8; 1];
+                if try!(self.inner.read(&mut byte)) == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Missing data descriptor"));
+                }
+                self.window.push_back(byte[0]);
+            }
+
+            if self.window.iter().cloned().eq(DATA_DESCRIPTOR_SIGNATURE.iter().cloned()) {
+                try!(self.read_trailer());
+                self.done = true;
+                break;
+            }
+
+            buf[written] = self.window.pop_front().unwrap();
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
 /// Methods for retreiving information on zip files
 impl<'a> ZipFile<'a> {
     fn get_reader(&mut self) -> &mut Read {
         match self.reader {
            ZipFileReader::Stored(ref mut r) => r as &mut Read,
+           ZipFileReader::Deflated(ref mut r) => r as &mut Read,
+           ZipFileReader::StoredDecrypted(ref mut r) => r as &mut Read,
+           ZipFileReader::DeflatedDecrypted(ref mut r) => r as &mut Read,
+           ZipFileReader::StoredStreaming(ref mut r) => r as &mut Read,
+           ZipFileReader::DeflatedStreaming(ref mut r) => r as &mut Read,
+        }
+    }
+
+    /// Once a streamed entry has been fully read, recover the CRC-32 and
+    /// sizes carried by its trailing data descriptor, validate the CRC
+    /// against what was actually decompressed, and write both back onto
+    /// `data` so `size()`/`compressed_size()` stop reporting the zeroes the
+    /// local header used as placeholders.
+    fn finish_streaming(&mut self) -> io::Result<()> {
+        if self.streaming_finished { return Ok(()); }
+
+        let computed_crc32 = match self.reader {
+            ZipFileReader::StoredStreaming(ref acc) => acc.crc32(),
+            ZipFileReader::DeflatedStreaming(ref acc) => acc.crc32(),
+            _ => return Ok(()),
+        };
+
+        // The decoder (for Deflated, in particular) stops asking its inner
+        // reader for more bytes as soon as it has enough to finish
+        // decompressing, which may be before the data descriptor has been
+        // reached. Drive the underlying DataDescriptorReader directly so
+        // locating the descriptor doesn't depend on the decoder's internal
+        // buffering happening to read far enough ahead.
+        {
+            let descriptor = match self.reader {
+                ZipFileReader::StoredStreaming(ref mut acc) => acc.get_mut(),
+                ZipFileReader::DeflatedStreaming(ref mut acc) => acc.get_mut().get_mut(),
+                _ => unreachable!(),
+            };
+            let mut scratch = [0u8; 4096];
+            while !descriptor.done {
+                if try!(descriptor.read(&mut scratch)) == 0 && !descriptor.done {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Missing data descriptor"));
+                }
+            }
+        }
+
+        let (crc32, compressed_size, uncompressed_size) = match self.reader {
+            ZipFileReader::StoredStreaming(ref acc) => {
+                let d = acc.get_ref();
+                (d.crc32, d.compressed_size, d.uncompressed_size)
+            },
+            ZipFileReader::DeflatedStreaming(ref acc) => {
+                let d = acc.get_ref().get_ref();
+                (d.crc32, d.compressed_size, d.uncompressed_size)
+            },
+            _ => unreachable!(),
+        };
+
+        if computed_crc32 != crc32 {
+            return Err(io::Error::new(io::ErrorKind::Other, "Invalid checksum"));
         }
+
+        let data = self.data.to_mut();
+        data.crc32 = crc32;
+        data.compressed_size = compressed_size;
+        data.uncompressed_size = uncompressed_size;
+        self.streaming_finished = true;
+        Ok(())
     }
     /// Get the name of the file
     pub fn name(&self) -> &str {
@@ -278,10 +585,420 @@ impl<'a> ZipFile<'a> {
     pub fn last_modified(&self) -> ::time::Tm {
         self.data.last_modified_time
     }
+    /// Get the Unix timestamp the file was last modified, from the extended
+    /// timestamp extra field if the archive provides one.
+    ///
+    /// Unlike `last_modified`, this has one-second rather than two-second
+    /// precision. The field is a signed 32-bit count of seconds since the
+    /// Unix epoch, so it covers the years 1901 to 2038.
+    pub fn last_modified_unix(&self) -> Option<i64> {
+        self.data.unix_mtime
+    }
+    /// Get unix mode for the file
+    pub fn unix_mode(&self) -> Option<u32> {
+        self.data.unix_mode
+    }
+    /// Returns whether the file is a directory
+    pub fn is_dir(&self) -> bool {
+        self.name().ends_with('/') || self.unix_mode().map_or(false, |mode| mode & S_IFDIR == S_IFDIR)
+    }
+    /// Rewrite the path, ignoring any path components with special meaning.
+    ///
+    /// - Absolute paths are made relative
+    /// - `..` path components are dropped
+    /// - Trailing slashes are dropped
+    ///
+    /// This is appropriate for extracting files from an untrusted zip archive,
+    /// since path traversal outside of a target directory is not possible.
+    pub fn sanitized_name(&self) -> ::std::path::PathBuf {
+        sanitize_file_name(&self.data.file_name)
+    }
+}
+
+/// Strips a zip entry's stored name down to its `Normal` path components,
+/// dropping `..`, root, and prefix components so the result can never escape
+/// the directory it's joined onto.
+fn sanitize_file_name(file_name: &str) -> ::std::path::PathBuf {
+    let mut path = ::std::path::PathBuf::new();
+
+    for component in ::std::path::Path::new(file_name).components() {
+        match component {
+            ::std::path::Component::Normal(os_str) => path.push(os_str),
+            _ => {},
+        }
+    }
+    path
 }
 
 impl<'a> Read for ZipFile<'a> {
      fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-         self.get_reader().read(buf)
+         let count = try!(self.get_reader().read(buf));
+         if count == 0 && !buf.is_empty() {
+             try!(self.finish_streaming());
+         }
+         Ok(count)
      }
 }
+
+/// Parse a single local file header from a non-seekable `Read` source, such
+/// as a pipe or socket, and hand back a `ZipFile` over its (possibly
+/// decompressed) data stream.
+///
+/// Call this repeatedly, advancing `reader` past each entry by fully
+/// consuming the returned `ZipFile`, until it returns `Ok(None)`, which
+/// means the central directory has been reached and there are no more
+/// entries to read.
+pub fn read_zipfile_from_stream<'a, R: Read>(reader: &'a mut R) -> ZipResult<Option<ZipFile<'a>>>
+{
+    let signature = try!(reader.read_u32::<LittleEndian>());
+
+    match signature
+    {
+        spec::LOCAL_FILE_HEADER_SIGNATURE => (),
+        spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE => return Ok(None),
+        _ => return Err(ZipError::InvalidArchive("Invalid local file header")),
+    }
+
+    try!(reader.read_u16::<LittleEndian>());
+    let flags = try!(reader.read_u16::<LittleEndian>());
+    let encrypted = flags & 1 == 1;
+    let is_utf8 = flags & (1 << 11) != 0;
+    let using_data_descriptor = flags & (1 << 3) != 0;
+    let compression_method = try!(reader.read_u16::<LittleEndian>());
+    let last_mod_time = try!(reader.read_u16::<LittleEndian>());
+    let last_mod_date = try!(reader.read_u16::<LittleEndian>());
+    let crc32 = try!(reader.read_u32::<LittleEndian>());
+    let compressed_size = try!(reader.read_u32::<LittleEndian>()) as u64;
+    let uncompressed_size = try!(reader.read_u32::<LittleEndian>()) as u64;
+    let file_name_length = try!(reader.read_u16::<LittleEndian>()) as usize;
+    let extra_field_length = try!(reader.read_u16::<LittleEndian>()) as usize;
+
+    let file_name_raw = try!(ReadPodExt::read_exact(reader, file_name_length));
+    try!(ReadPodExt::read_exact(reader, extra_field_length));
+
+    let file_name = match is_utf8
+    {
+        true => String::from_utf8_lossy(&*file_name_raw).into_owned(),
+        false => file_name_raw.from_cp437(),
+    };
+
+    if encrypted
+    {
+        return unsupported_zip_error("Encrypted files are not supported in streaming mode")
+    }
+
+    let data = ZipFileData
+    {
+        encrypted: encrypted,
+        compression_method: CompressionMethod::from_u16(compression_method),
+        last_modified_time: util::msdos_datetime_to_tm(last_mod_time, last_mod_date),
+        crc32: crc32,
+        compressed_size: compressed_size,
+        uncompressed_size: uncompressed_size,
+        file_name: file_name,
+        file_comment: String::new(),
+        header_start: 0,
+        data_start: 0,
+        unix_mode: None,
+        unix_mtime: None,
+        unix_atime: None,
+        unix_ctime: None,
+    };
+
+    let raw_reader = reader as &mut Read;
+
+    let zip_reader = if using_data_descriptor
+    {
+        match data.compression_method
+        {
+            CompressionMethod::Stored => ZipFileReader::StoredStreaming(Crc32Accumulator::new(DataDescriptorReader::new(raw_reader))),
+            CompressionMethod::Deflated => ZipFileReader::DeflatedStreaming(Crc32Accumulator::new(DeflateDecoder::new(DataDescriptorReader::new(raw_reader)))),
+            _ => return unsupported_zip_error("Compression method not supported"),
+        }
+    }
+    else
+    {
+        let take_reader = raw_reader.take(compressed_size);
+        match data.compression_method
+        {
+            CompressionMethod::Stored => ZipFileReader::Stored(Crc32Reader::new(take_reader, crc32)),
+            CompressionMethod::Deflated => ZipFileReader::Deflated(Crc32Reader::new(DeflateDecoder::new(take_reader), crc32)),
+            _ => return unsupported_zip_error("Compression method not supported"),
+        }
+    };
+
+    Ok(Some(ZipFile { data: Cow::Owned(data), reader: zip_reader, streaming_finished: false }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::sanitize_file_name;
+    use std::path::PathBuf;
+
+    #[test]
+    fn sanitize_strips_parent_dir_components() {
+        assert_eq!(sanitize_file_name("../../etc/passwd"), PathBuf::from("etc/passwd"));
+        assert_eq!(sanitize_file_name("foo/../../bar"), PathBuf::from("foo/bar"));
+    }
+
+    #[test]
+    fn sanitize_strips_absolute_paths() {
+        assert_eq!(sanitize_file_name("/etc/passwd"), PathBuf::from("etc/passwd"));
+    }
+
+    #[test]
+    fn sanitize_leaves_plain_relative_paths_alone() {
+        assert_eq!(sanitize_file_name("src/main.rs"), PathBuf::from("src/main.rs"));
+    }
+
+    use super::read_zipfile_from_stream;
+    use podio::{WritePodExt, LittleEndian};
+    use ::crc32::crc32_update;
+    use ::compression::CompressionMethod;
+    use ::types::ZipFileData;
+    use ::spec;
+    use std::io;
+    use std::io::prelude::*;
+
+    /// Builds a single streamed entry (general purpose bit 3 set, so the
+    /// local header's sizes/CRC are zeroed and a trailing data descriptor
+    /// carries the real values) followed by a central directory signature,
+    /// mimicking the layout `read_zipfile_from_stream` expects to walk.
+    fn build_streamed_entry(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut crc = 0xFFFFFFFFu32;
+        for &byte in content {
+            crc = crc32_update(crc, byte);
+        }
+        crc ^= 0xFFFFFFFF;
+
+        let mut out = Vec::new();
+        out.write_u32::<LittleEndian>(spec::LOCAL_FILE_HEADER_SIGNATURE).unwrap();
+        out.write_u16::<LittleEndian>(20).unwrap();
+        out.write_u16::<LittleEndian>(1 << 3).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(name.len() as u16).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_all(name.as_bytes()).unwrap();
+        out.write_all(content).unwrap();
+        out.write_u32::<LittleEndian>(0x08074b50).unwrap();
+        out.write_u32::<LittleEndian>(crc).unwrap();
+        out.write_u32::<LittleEndian>(content.len() as u32).unwrap();
+        out.write_u32::<LittleEndian>(content.len() as u32).unwrap();
+        out.write_u32::<LittleEndian>(spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE).unwrap();
+        out
+    }
+
+    #[test]
+    fn streaming_reconciles_crc_and_sizes_from_data_descriptor() {
+        let bytes = build_streamed_entry("hello.txt", b"hello world");
+        let mut cursor = io::Cursor::new(bytes);
+
+        let mut file = read_zipfile_from_stream(&mut cursor).unwrap().unwrap();
+        assert_eq!(file.size(), 0);
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).unwrap();
+
+        assert_eq!(content, b"hello world");
+        assert_eq!(file.size(), 11);
+        assert_eq!(file.compressed_size(), 11);
+
+        assert!(read_zipfile_from_stream(&mut cursor).unwrap().is_none());
+    }
+
+    #[test]
+    fn streaming_rejects_a_forged_data_descriptor_crc() {
+        let mut bytes = build_streamed_entry("hello.txt", b"hello world");
+        // Corrupt the CRC-32 stored in the data descriptor, 12 bytes before
+        // the trailing central-directory signature we appended above.
+        let crc_offset = bytes.len() - 16;
+        bytes[crc_offset] ^= 0xff;
+        let mut cursor = io::Cursor::new(bytes);
+
+        let mut file = read_zipfile_from_stream(&mut cursor).unwrap().unwrap();
+        let mut content = Vec::new();
+        assert!(file.read_to_end(&mut content).is_err());
+    }
+
+    /// Deterministic pseudo-random bytes, incompressible enough that deflating
+    /// them still produces a compressed stream larger than a single internal
+    /// decoder buffer, so the descriptor can't be found by incidental read-ahead.
+    fn pseudo_random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x2545F4914F6CDD1Du64;
+        let mut out = Vec::with_capacity(len);
+        for _ in 0..len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            out.push(state as u8);
+        }
+        out
+    }
+
+    /// Same layout as `build_streamed_entry`, but Deflated rather than Stored,
+    /// so the entry is read back through a `DeflateDecoder` wrapping the
+    /// `DataDescriptorReader` rather than the reader directly.
+    fn build_deflated_streamed_entry(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut crc = 0xFFFFFFFFu32;
+        for &byte in content {
+            crc = crc32_update(crc, byte);
+        }
+        crc ^= 0xFFFFFFFF;
+
+        let mut encoder = ::flate2::write::DeflateEncoder::new(Vec::new(), ::flate2::Compression::fast());
+        encoder.write_all(content).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut out = Vec::new();
+        out.write_u32::<LittleEndian>(spec::LOCAL_FILE_HEADER_SIGNATURE).unwrap();
+        out.write_u16::<LittleEndian>(20).unwrap();
+        out.write_u16::<LittleEndian>(1 << 3).unwrap();
+        out.write_u16::<LittleEndian>(8).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u32::<LittleEndian>(0).unwrap();
+        out.write_u16::<LittleEndian>(name.len() as u16).unwrap();
+        out.write_u16::<LittleEndian>(0).unwrap();
+        out.write_all(name.as_bytes()).unwrap();
+        out.write_all(&compressed).unwrap();
+        out.write_u32::<LittleEndian>(0x08074b50).unwrap();
+        out.write_u32::<LittleEndian>(crc).unwrap();
+        out.write_u32::<LittleEndian>(compressed.len() as u32).unwrap();
+        out.write_u32::<LittleEndian>(content.len() as u32).unwrap();
+        out.write_u32::<LittleEndian>(spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE).unwrap();
+        out
+    }
+
+    #[test]
+    fn deflated_streaming_reconciles_crc_and_sizes_from_data_descriptor() {
+        let content = pseudo_random_bytes(64 * 1024);
+        let bytes = build_deflated_streamed_entry("big.bin", &content);
+        let mut cursor = io::Cursor::new(bytes);
+
+        let mut file = read_zipfile_from_stream(&mut cursor).unwrap().unwrap();
+        assert_eq!(file.size(), 0);
+
+        let mut read_back = Vec::new();
+        file.read_to_end(&mut read_back).unwrap();
+
+        assert_eq!(read_back, content);
+        assert_eq!(file.size(), content.len() as u64);
+
+        assert!(read_zipfile_from_stream(&mut cursor).unwrap().is_none());
+    }
+
+    #[test]
+    fn deflated_streaming_rejects_a_forged_data_descriptor_crc() {
+        let content = pseudo_random_bytes(64 * 1024);
+        let mut bytes = build_deflated_streamed_entry("big.bin", &content);
+        // Corrupt the CRC-32 stored in the data descriptor, 12 bytes before
+        // the trailing central-directory signature we appended above.
+        let crc_offset = bytes.len() - 16;
+        bytes[crc_offset] ^= 0xff;
+        let mut cursor = io::Cursor::new(bytes);
+
+        let mut file = read_zipfile_from_stream(&mut cursor).unwrap().unwrap();
+        let mut read_back = Vec::new();
+        assert!(file.read_to_end(&mut read_back).is_err());
+    }
+
+    fn dummy_zip_file_data() -> ZipFileData {
+        ZipFileData {
+            encrypted: false,
+            compression_method: CompressionMethod::Stored,
+            last_modified_time: ::util::msdos_datetime_to_tm(0, 0),
+            crc32: 0,
+            compressed_size: ::std::u32::MAX as u64,
+            uncompressed_size: ::std::u32::MAX as u64,
+            file_name: String::new(),
+            file_comment: String::new(),
+            header_start: ::std::u32::MAX as u64,
+            data_start: 0,
+            unix_mode: None,
+            unix_mtime: None,
+            unix_atime: None,
+            unix_ctime: None,
+        }
+    }
+
+    #[test]
+    fn parse_extra_field_reads_zip64_replacements() {
+        let mut extra = Vec::new();
+        extra.write_u16::<LittleEndian>(0x0001).unwrap();
+        extra.write_u16::<LittleEndian>(28).unwrap(); // 8 + 8 + 8 + 4
+        extra.write_u64::<LittleEndian>(0x1_0000_0001).unwrap(); // uncompressed size
+        extra.write_u64::<LittleEndian>(0x1_0000_0002).unwrap(); // compressed size
+        extra.write_u64::<LittleEndian>(0x1_0000_0003).unwrap(); // local header offset
+        extra.write_u32::<LittleEndian>(0).unwrap(); // disk start, unused
+
+        let mut file = dummy_zip_file_data();
+        super::parse_extra_field(&mut file, &extra, true, true, true).unwrap();
+
+        assert_eq!(file.uncompressed_size, 0x1_0000_0001);
+        assert_eq!(file.compressed_size, 0x1_0000_0002);
+        assert_eq!(file.header_start, 0x1_0000_0003);
+    }
+
+    #[test]
+    fn parse_extra_field_skips_zip64_fields_when_sizes_are_not_sentinels() {
+        let mut file = dummy_zip_file_data();
+        file.uncompressed_size = 5;
+        file.compressed_size = 5;
+        file.header_start = 5;
+
+        // A ZIP64 block is present, but none of the 32-bit fields were
+        // sentinels, so its value must not replace the already-correct ones.
+        let mut extra = Vec::new();
+        extra.write_u16::<LittleEndian>(0x0001).unwrap();
+        extra.write_u16::<LittleEndian>(8).unwrap();
+        extra.write_u64::<LittleEndian>(0x1_0000_0001).unwrap();
+        super::parse_extra_field(&mut file, &extra, false, false, false).unwrap();
+
+        assert_eq!(file.uncompressed_size, 5);
+        assert_eq!(file.compressed_size, 5);
+        assert_eq!(file.header_start, 5);
+    }
+
+    #[test]
+    fn parse_extra_field_reads_extended_timestamps() {
+        let mut extra = Vec::new();
+        extra.write_u16::<LittleEndian>(0x5455).unwrap();
+        extra.write_u16::<LittleEndian>(13).unwrap(); // 1 flags byte + 3 * 4 byte timestamps
+        extra.write_u8(0b111).unwrap(); // mtime, atime, and ctime all present
+        extra.write_i32::<LittleEndian>(1_000_000).unwrap();
+        extra.write_i32::<LittleEndian>(1_000_001).unwrap();
+        extra.write_i32::<LittleEndian>(1_000_002).unwrap();
+
+        let mut file = dummy_zip_file_data();
+        super::parse_extra_field(&mut file, &extra, false, false, false).unwrap();
+
+        assert_eq!(file.unix_mtime, Some(1_000_000));
+        assert_eq!(file.unix_atime, Some(1_000_001));
+        assert_eq!(file.unix_ctime, Some(1_000_002));
+    }
+
+    #[test]
+    fn parse_extra_field_extended_timestamp_mtime_only() {
+        // The central directory copy of this field commonly only carries the
+        // mtime, even though the local header copy may have more.
+        let mut extra = Vec::new();
+        extra.write_u16::<LittleEndian>(0x5455).unwrap();
+        extra.write_u16::<LittleEndian>(5).unwrap(); // 1 flags byte + 1 timestamp
+        extra.write_u8(0b111).unwrap(); // flags claim all three are present
+        extra.write_i32::<LittleEndian>(42).unwrap();
+
+        let mut file = dummy_zip_file_data();
+        super::parse_extra_field(&mut file, &extra, false, false, false).unwrap();
+
+        assert_eq!(file.unix_mtime, Some(42));
+        assert_eq!(file.unix_atime, None);
+        assert_eq!(file.unix_ctime, None);
+    }
+}
@@ -0,0 +1,59 @@
+//! Error types that can be emitted from this library
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Generic result type with ZipError as its error variant
+pub type ZipResult<A> = Result<A, ZipError>;
+
+/// Error type for Zip
+#[derive(Debug)]
+pub enum ZipError
+{
+    /// An Error caused by I/O
+    Io(io::Error),
+
+    /// This file is probably not a zip archive
+    InvalidArchive(&'static str),
+
+    /// This archive is not supported
+    UnsupportedArchive(&'static str),
+
+    /// The requested file could not be found in the archive
+    FileNotFound,
+
+    /// The password provided for an encrypted file was incorrect
+    InvalidPassword,
+}
+
+impl From<io::Error> for ZipError {
+    fn from(err: io::Error) -> ZipError
+    {
+        ZipError::Io(err)
+    }
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ZipError::Io(ref io_err) => write!(fmt, "{}", io_err),
+            ZipError::InvalidArchive(ref reason) => write!(fmt, "invalid Zip archive: {}", reason),
+            ZipError::UnsupportedArchive(ref reason) => write!(fmt, "unsupported Zip archive: {}", reason),
+            ZipError::FileNotFound => write!(fmt, "specified file not found in archive"),
+            ZipError::InvalidPassword => write!(fmt, "invalid password for file in archive"),
+        }
+    }
+}
+
+impl error::Error for ZipError {
+    fn description(&self) -> &str {
+        match *self {
+            ZipError::Io(ref io_err) => error::Error::description(io_err),
+            ZipError::InvalidArchive(..) => "invalid Zip archive",
+            ZipError::UnsupportedArchive(..) => "unsupported Zip archive",
+            ZipError::FileNotFound => "specified file not found in archive",
+            ZipError::InvalidPassword => "invalid password for file in archive",
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Constants and low-level structures used throughout the library
+
+use std::io;
+use std::io::prelude::*;
+use podio::{ReadPodExt, LittleEndian};
+use result::{ZipResult, ZipError};
+
+pub const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+pub const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+const CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+
+pub struct CentralDirectoryEnd
+{
+    pub disk_number: u16,
+    pub disk_with_central_directory: u16,
+    pub number_of_files_on_this_disk: u64,
+    pub number_of_files: u64,
+    pub central_directory_size: u64,
+    pub central_directory_offset: u64,
+    pub zip_file_comment: Vec<u8>,
+}
+
+impl CentralDirectoryEnd {
+    pub fn parse<T: Read>(reader: &mut T) -> ZipResult<CentralDirectoryEnd>
+    {
+        let magic = try!(reader.read_u32::<LittleEndian>());
+        if magic != CENTRAL_DIRECTORY_END_SIGNATURE
+        {
+            return Err(ZipError::InvalidArchive("Invalid digital signature header"));
+        }
+        let disk_number = try!(reader.read_u16::<LittleEndian>());
+        let disk_with_central_directory = try!(reader.read_u16::<LittleEndian>());
+        let number_of_files_on_this_disk = try!(reader.read_u16::<LittleEndian>());
+        let number_of_files = try!(reader.read_u16::<LittleEndian>());
+        let central_directory_size = try!(reader.read_u32::<LittleEndian>());
+        let central_directory_offset = try!(reader.read_u32::<LittleEndian>());
+        let comment_length = try!(reader.read_u16::<LittleEndian>()) as usize;
+        let zip_file_comment = try!(ReadPodExt::read_exact(reader, comment_length));
+
+        Ok(CentralDirectoryEnd
+        {
+            disk_number: disk_number,
+            disk_with_central_directory: disk_with_central_directory,
+            number_of_files_on_this_disk: number_of_files_on_this_disk as u64,
+            number_of_files: number_of_files as u64,
+            central_directory_size: central_directory_size as u64,
+            central_directory_offset: central_directory_offset as u64,
+            zip_file_comment: zip_file_comment,
+        })
+    }
+
+    pub fn find_and_parse<T: Read+io::Seek>(reader: &mut T) -> ZipResult<CentralDirectoryEnd>
+    {
+        const HEADER_SIZE: u64 = 22;
+        const BYTES_BETWEEN_MAGIC_AND_COMMENT_SIZE: u64 = HEADER_SIZE - 6;
+        let file_length = try!(reader.seek(io::SeekFrom::End(0)));
+
+        if file_length < HEADER_SIZE { return Err(ZipError::InvalidArchive("Invalid zip header")); }
+
+        let search_upper_bound = if file_length - HEADER_SIZE < ::std::u16::MAX as u64
+        {
+            0
+        }
+        else
+        {
+            file_length - HEADER_SIZE - ::std::u16::MAX as u64
+        };
+
+        let mut pos = file_length - HEADER_SIZE;
+        while pos >= search_upper_bound
+        {
+            try!(reader.seek(io::SeekFrom::Start(pos)));
+            if try!(reader.read_u32::<LittleEndian>()) == CENTRAL_DIRECTORY_END_SIGNATURE
+            {
+                try!(reader.seek(io::SeekFrom::Current(BYTES_BETWEEN_MAGIC_AND_COMMENT_SIZE as i64)));
+                let cde_start_pos = pos;
+                try!(reader.seek(io::SeekFrom::Start(cde_start_pos)));
+                let mut footer = try!(CentralDirectoryEnd::parse(reader));
+
+                if footer.number_of_files == 0xFFFF as u64
+                    || footer.central_directory_offset == 0xFFFFFFFF as u64
+                {
+                    // Might be a ZIP64 file, try to find the locator and record
+                    if let Ok(locator64) = Zip64CentralDirectoryEndLocator::find_and_parse(reader, cde_start_pos)
+                    {
+                        try!(reader.seek(io::SeekFrom::Start(locator64.end_of_central_directory_offset)));
+                        let record64 = try!(Zip64CentralDirectoryEnd::parse(reader));
+
+                        footer.number_of_files_on_this_disk = record64.number_of_files_on_this_disk;
+                        footer.number_of_files = record64.number_of_files;
+                        footer.central_directory_size = record64.central_directory_size;
+                        footer.central_directory_offset = record64.central_directory_offset;
+                    }
+                }
+
+                return Ok(footer);
+            }
+            if pos == 0 { break; }
+            pos -= 1;
+        }
+        Err(ZipError::InvalidArchive("Could not find central directory end"))
+    }
+}
+
+struct Zip64CentralDirectoryEndLocator
+{
+    end_of_central_directory_offset: u64,
+}
+
+impl Zip64CentralDirectoryEndLocator {
+    // The locator sits immediately before the classic EOCD record: 4 bytes
+    // signature + 4 bytes disk number + 8 bytes offset + 4 bytes disk count.
+    fn find_and_parse<T: Read+io::Seek>(reader: &mut T, cde_start_pos: u64) -> ZipResult<Zip64CentralDirectoryEndLocator>
+    {
+        const LOCATOR_SIZE: u64 = 20;
+        if cde_start_pos < LOCATOR_SIZE
+        {
+            return Err(ZipError::InvalidArchive("Could not find ZIP64 locator"));
+        }
+        try!(reader.seek(io::SeekFrom::Start(cde_start_pos - LOCATOR_SIZE)));
+
+        let magic = try!(reader.read_u32::<LittleEndian>());
+        if magic != ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE
+        {
+            return Err(ZipError::InvalidArchive("Invalid ZIP64 locator signature"));
+        }
+        try!(reader.read_u32::<LittleEndian>());
+        let end_of_central_directory_offset = try!(reader.read_u64::<LittleEndian>());
+        try!(reader.read_u32::<LittleEndian>());
+
+        Ok(Zip64CentralDirectoryEndLocator { end_of_central_directory_offset: end_of_central_directory_offset })
+    }
+}
+
+struct Zip64CentralDirectoryEnd
+{
+    number_of_files_on_this_disk: u64,
+    number_of_files: u64,
+    central_directory_size: u64,
+    central_directory_offset: u64,
+}
+
+impl Zip64CentralDirectoryEnd {
+    fn parse<T: Read>(reader: &mut T) -> ZipResult<Zip64CentralDirectoryEnd>
+    {
+        let magic = try!(reader.read_u32::<LittleEndian>());
+        if magic != ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE
+        {
+            return Err(ZipError::InvalidArchive("Invalid ZIP64 central directory end signature"));
+        }
+
+        try!(reader.read_u64::<LittleEndian>()); // size of this record
+        try!(reader.read_u16::<LittleEndian>()); // version made by
+        try!(reader.read_u16::<LittleEndian>()); // version needed to extract
+        try!(reader.read_u32::<LittleEndian>()); // number of this disk
+        try!(reader.read_u32::<LittleEndian>()); // disk with central directory
+        let number_of_files_on_this_disk = try!(reader.read_u64::<LittleEndian>());
+        let number_of_files = try!(reader.read_u64::<LittleEndian>());
+        let central_directory_size = try!(reader.read_u64::<LittleEndian>());
+        let central_directory_offset = try!(reader.read_u64::<LittleEndian>());
+
+        Ok(Zip64CentralDirectoryEnd
+        {
+            number_of_files_on_this_disk: number_of_files_on_this_disk,
+            number_of_files: number_of_files,
+            central_directory_size: central_directory_size,
+            central_directory_offset: central_directory_offset,
+        })
+    }
+}
@@ -0,0 +1,40 @@
+//! Types that specify what is contained in a ZIP.
+
+use compression::CompressionMethod;
+use time;
+
+/// Metadata for a single file in a ZIP archive, gathered from the central
+/// directory and local header.
+#[derive(Debug, Clone)]
+pub struct ZipFileData
+{
+    /// True if the file is encrypted with the traditional PKWARE encryption
+    pub encrypted: bool,
+    /// Compression method used to store the file
+    pub compression_method: CompressionMethod,
+    /// Last modified time. This will only have a 2 second precision.
+    pub last_modified_time: time::Tm,
+    /// CRC32 checksum
+    pub crc32: u32,
+    /// Size of the file in the archive
+    pub compressed_size: u64,
+    /// Size of the file when extracted
+    pub uncompressed_size: u64,
+    /// Name of the file
+    pub file_name: String,
+    /// File comment
+    pub file_comment: String,
+    /// Specifies where the local header of the file starts
+    pub header_start: u64,
+    /// Specifies where the compressed data of the file starts
+    pub data_start: u64,
+    /// Unix permissions and file type, if the archive was created on a Unix host
+    pub unix_mode: Option<u32>,
+    /// Last modification time in whole seconds since the Unix epoch, from the
+    /// extended timestamp extra field, if present
+    pub unix_mtime: Option<i64>,
+    /// Last access time, from the extended timestamp extra field, if present
+    pub unix_atime: Option<i64>,
+    /// Creation time, from the extended timestamp extra field, if present
+    pub unix_ctime: Option<i64>,
+}
@@ -0,0 +1,29 @@
+//! Helper functions for working with MS-DOS date/time.
+
+use time;
+
+/// Converts an MS-DOS time and date to a time::Tm
+pub fn msdos_datetime_to_tm(time: u16, date: u16) -> time::Tm
+{
+    let seconds = (time & 0b0000000000011111) << 1;
+    let minutes = (time & 0b0000011111100000) >> 5;
+    let hours = (time & 0b1111100000000000) >> 11;
+    let days = date & 0b0000000000011111;
+    let months = (date & 0b0000000111100000) >> 5;
+    let years = (date & 0b1111111000000000) >> 9;
+
+    time::Tm
+    {
+        tm_sec: seconds as i32,
+        tm_min: minutes as i32,
+        tm_hour: hours as i32,
+        tm_mday: days as i32,
+        tm_mon: months as i32 - 1,
+        tm_year: years as i32 + 1980 - 1900,
+        tm_wday: 0,
+        tm_yday: 0,
+        tm_isdst: -1,
+        tm_utcoff: 0,
+        tm_nsec: 0,
+    }
+}
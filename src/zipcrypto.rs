@@ -0,0 +1,127 @@
+//! Implementation of the traditional PKWARE ("ZipCrypto") encryption scheme
+
+use std::io;
+use std::io::prelude::*;
+use crc32::crc32_update;
+
+/// Size in bytes of the encryption header prepended to an encrypted entry's data
+pub const ZIPCRYPTO_HEADER_SIZE: usize = 12;
+
+/// The three 32-bit keys that make up the ZipCrypto cipher state
+pub struct ZipCryptoKeys {
+    key0: u32,
+    key1: u32,
+    key2: u32,
+}
+
+impl ZipCryptoKeys {
+    /// Create a new set of keys, initialized to the constants specified by the format
+    pub fn new() -> ZipCryptoKeys {
+        ZipCryptoKeys { key0: 0x12345678, key1: 0x23456789, key2: 0x34567890 }
+    }
+
+    /// Seed the keys with a password, one byte at a time
+    pub fn derive(password: &[u8]) -> ZipCryptoKeys {
+        let mut keys = ZipCryptoKeys::new();
+        for &byte in password {
+            keys.update(byte);
+        }
+        keys
+    }
+
+    fn update(&mut self, byte: u8) {
+        self.key0 = crc32_update(self.key0, byte);
+        self.key1 = self.key1.wrapping_add(self.key0 & 0xff).wrapping_mul(134775813).wrapping_add(1);
+        self.key2 = crc32_update(self.key2, (self.key1 >> 24) as u8);
+    }
+
+    /// Decrypt a single byte of cipher text, advancing the keys with the resulting plaintext
+    pub fn decrypt_byte(&mut self, c: u8) -> u8 {
+        let temp = (self.key2 | 2) as u16;
+        let k = ((temp.wrapping_mul(temp ^ 1)) >> 8) as u8;
+        let plain = c ^ k;
+        self.update(plain);
+        plain
+    }
+}
+
+/// A reader that transparently decrypts a traditional PKWARE encrypted stream.
+///
+/// The 12-byte encryption header must already have been consumed and
+/// validated from the underlying reader before constructing this type.
+pub struct ZipCryptoReader<R> {
+    inner: R,
+    keys: ZipCryptoKeys,
+}
+
+impl<R: Read> ZipCryptoReader<R> {
+    /// Wrap `inner`, decrypting bytes read from it with `keys`
+    pub fn new(inner: R, keys: ZipCryptoKeys) -> ZipCryptoReader<R> {
+        ZipCryptoReader { inner: inner, keys: keys }
+    }
+}
+
+impl<R: Read> Read for ZipCryptoReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = try!(self.inner.read(buf));
+        for byte in &mut buf[0..count] {
+            *byte = self.keys.decrypt_byte(*byte);
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ZipCryptoKeys, ZipCryptoReader};
+    use std::io::Read;
+
+    /// Encrypt with the same keystream `decrypt_byte` uses, mirroring how a
+    /// real ZipCrypto writer would produce the cipher text this module reads
+    fn encrypt(password: &[u8], plain: &[u8]) -> Vec<u8> {
+        let mut keys = ZipCryptoKeys::derive(password);
+        plain.iter().map(|&p| {
+            let temp = (keys.key2 | 2) as u16;
+            let k = ((temp.wrapping_mul(temp ^ 1)) >> 8) as u8;
+            keys.update(p);
+            p ^ k
+        }).collect()
+    }
+
+    #[test]
+    fn decrypt_byte_round_trips() {
+        let password = b"s3cr3t";
+        let plain = b"the quick brown fox jumps over the lazy dog";
+        let cipher = encrypt(password, plain);
+
+        let keys = ZipCryptoKeys::derive(password);
+        let mut reader = ZipCryptoReader::new(&cipher[..], keys);
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+
+        assert_eq!(&decrypted[..], &plain[..]);
+    }
+
+    #[test]
+    fn decrypt_byte_wrong_password_produces_garbage() {
+        let plain = b"the quick brown fox jumps over the lazy dog";
+        let cipher = encrypt(b"right-password", plain);
+
+        let keys = ZipCryptoKeys::derive(b"wrong-password");
+        let mut reader = ZipCryptoReader::new(&cipher[..], keys);
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+
+        assert_ne!(&decrypted[..], &plain[..]);
+    }
+
+    #[test]
+    fn update_does_not_overflow() {
+        // Regression test: key1 used to overflow with plain `+` once it had
+        // drifted away from its initial value, panicking in debug builds.
+        let mut keys = ZipCryptoKeys::derive(b"password");
+        for byte in 0..=255u8 {
+            keys.update(byte);
+        }
+    }
+}